@@ -5,12 +5,18 @@ mod errors;
 #[ink::contract]
 mod az_event_registration {
     use crate::errors::AzEventRegistrationError;
-    use ink::{codegen::EmitEvent, reflect::ContractEventBase, storage::Mapping};
+    use ink::{
+        codegen::EmitEvent, prelude::vec::Vec, reflect::ContractEventBase, storage::Mapping,
+    };
 
     // === TYPES ===
     type Event = <AzEventRegistration as ContractEventBase>::Type;
     type Result<T> = core::result::Result<T, AzEventRegistrationError>;
 
+    // === CONSTANTS ===
+    const MIN_TTL: Timestamp = 60_000; // 1 minute
+    const MAX_TTL: Timestamp = 2_592_000_000; // 30 days
+
     // === EVENTS ===
     #[ink(event)]
     pub struct Register {
@@ -28,12 +34,28 @@ mod az_event_registration {
         referrer: Option<AccountId>,
     }
 
+    #[ink(event)]
+    pub struct Upgrade {
+        #[ink(topic)]
+        address: AccountId,
+    }
+
     // === STRUCTS ===
     #[derive(Debug, Clone, scale::Encode, scale::Decode)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub struct Config {
         admin: AccountId,
         deadline: Timestamp,
+        invitations_only: bool,
+        standard_cap: u32,
+        vip_cap: u32,
+    }
+
+    #[derive(scale::Decode, scale::Encode, Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Tier {
+        Standard,
+        Vip,
     }
 
     #[derive(scale::Decode, scale::Encode, Debug, Clone, PartialEq)]
@@ -44,6 +66,19 @@ mod az_event_registration {
     pub struct Registration {
         address: AccountId,
         referrer: Option<AccountId>,
+        expires_at: Timestamp,
+        tier: Tier,
+    }
+
+    #[derive(scale::Decode, scale::Encode, Debug, Clone, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Invitation {
+        max_uses: u32,
+        used: u32,
+        expiry: Timestamp,
     }
 
     // === CONTRACT ===
@@ -51,15 +86,50 @@ mod az_event_registration {
     pub struct AzEventRegistration {
         admin: AccountId,
         deadline: Timestamp,
+        invitations_only: bool,
         registrations: Mapping<AccountId, Registration>,
+        registration_count: u64,
+        registration_index: Mapping<u64, AccountId>,
+        // pagination bookkeeping only — not part of Registration, so it never leaks into
+        // the ABI of show/register/update/registrations
+        registration_indices: Mapping<AccountId, u64>,
+        invitations: Mapping<Hash, Invitation>,
+        invitation_codes: Vec<Hash>,
+        referral_counts: Mapping<AccountId, u32>,
+        referrer_count: u64,
+        referrer_index: Mapping<u64, AccountId>,
+        referrer_indexed: Mapping<AccountId, ()>,
+        standard_cap: u32,
+        vip_cap: u32,
+        standard_count: u32,
+        vip_count: u32,
     }
     impl AzEventRegistration {
         #[ink(constructor)]
-        pub fn new(deadline: Timestamp) -> Self {
+        pub fn new(
+            deadline: Timestamp,
+            invitations_only: bool,
+            standard_cap: u32,
+            vip_cap: u32,
+        ) -> Self {
             Self {
                 admin: Self::env().caller(),
                 deadline,
+                invitations_only,
                 registrations: Mapping::default(),
+                registration_count: 0,
+                registration_index: Mapping::default(),
+                registration_indices: Mapping::default(),
+                invitations: Mapping::default(),
+                invitation_codes: Vec::new(),
+                referral_counts: Mapping::default(),
+                referrer_count: 0,
+                referrer_index: Mapping::default(),
+                referrer_indexed: Mapping::default(),
+                standard_cap,
+                vip_cap,
+                standard_count: 0,
+                vip_count: 0,
             }
         }
 
@@ -71,38 +141,183 @@ mod az_event_registration {
             Config {
                 admin: self.admin,
                 deadline: self.deadline,
+                invitations_only: self.invitations_only,
+                standard_cap: self.standard_cap,
+                vip_cap: self.vip_cap,
             }
         }
 
         #[ink(message)]
         pub fn show(&self, address: AccountId) -> Result<Registration> {
-            self.registrations
+            let registration: Registration = self
+                .registrations
                 .get(address)
-                .ok_or(AzEventRegistrationError::NotFound(
+                .ok_or(AzEventRegistrationError::NotFound("Registration".to_string()))?;
+            if registration.expires_at < Self::env().block_timestamp() {
+                return Err(AzEventRegistrationError::NotFound(
                     "Registration".to_string(),
-                ))
+                ));
+            }
+
+            Ok(registration)
+        }
+
+        // cookie is the index of the last registration returned by the previous call
+        #[ink(message)]
+        pub fn registrations(
+            &self,
+            cookie: Option<u64>,
+            limit: u64,
+        ) -> (Vec<Registration>, Option<u64>) {
+            if limit == 0 {
+                return (Vec::new(), cookie);
+            }
+
+            let mut result: Vec<Registration> = Vec::new();
+            let mut next_cookie: Option<u64> = None;
+            let mut i: u64 = cookie.map(|c| c + 1).unwrap_or(0);
+            while i < self.registration_count {
+                if let Some(address) = self.registration_index.get(i) {
+                    if let Ok(registration) = self.show(address) {
+                        result.push(registration);
+                        if result.len() as u64 == limit {
+                            next_cookie = Some(i);
+                            break;
+                        }
+                    }
+                }
+                i += 1;
+            }
+
+            (result, next_cookie)
+        }
+
+        #[ink(message)]
+        pub fn list_invitations(&self) -> Vec<(Hash, Invitation)> {
+            self.invitation_codes
+                .iter()
+                .filter_map(|code| self.invitations.get(code).map(|invitation| (*code, invitation)))
+                .collect()
+        }
+
+        // counted as of the last register/update/destroy that touched the referring
+        // registration, not re-validated against it on read: if a registration naming
+        // `address` as referrer later lapses on its own (expires without being destroyed
+        // or renewed), its contribution to this count is not removed. only an explicit
+        // destroy or a referrer change decrements it.
+        #[ink(message)]
+        pub fn referral_count(&self, address: AccountId) -> u32 {
+            self.referral_counts.get(address).unwrap_or(0)
+        }
+
+        // cookie is the index of the last referrer returned by the previous call,
+        // ranked by referral count, highest first.
+        //
+        // unlike change_tier's capacity check, this one has no O(1) substitute: ranking
+        // requires comparing every live referrer's count, so cost scales with referrer_count
+        // regardless of limit. that's acceptable here because, unlike change_tier, this is a
+        // read-only `&self` message — it's meant to be called off-chain via RPC and doesn't
+        // carry the per-transaction gas cost that motivated reverting change_tier's scan.
+        //
+        // counts carry referral_count's same staleness: a registration naming a referrer
+        // that later lapses unrenewed still counts towards that referrer's rank here.
+        #[ink(message)]
+        pub fn top_referrers(
+            &self,
+            cookie: Option<u64>,
+            limit: u64,
+        ) -> (Vec<(AccountId, u32)>, Option<u64>) {
+            if limit == 0 {
+                return (Vec::new(), cookie);
+            }
+
+            let mut ranked: Vec<(AccountId, u32)> = Vec::new();
+            for i in 0..self.referrer_count {
+                if let Some(address) = self.referrer_index.get(i) {
+                    let count = self.referral_counts.get(address).unwrap_or(0);
+                    if count > 0 {
+                        ranked.push((address, count));
+                    }
+                }
+            }
+            ranked.sort_by(|a, b| b.1.cmp(&a.1));
+
+            let mut result: Vec<(AccountId, u32)> = Vec::new();
+            let mut next_cookie: Option<u64> = None;
+            let mut i: usize = cookie.map(|c| c as usize + 1).unwrap_or(0);
+            while i < ranked.len() {
+                result.push(ranked[i]);
+                if result.len() as u64 == limit {
+                    if i + 1 < ranked.len() {
+                        next_cookie = Some(i as u64);
+                    }
+                    break;
+                }
+                i += 1;
+            }
+
+            (result, next_cookie)
         }
 
         // === HANDLES ===
         #[ink(message)]
         pub fn destroy(&mut self) -> Result<()> {
             let caller: AccountId = Self::env().caller();
-            self.show(caller)?;
+            // unlike show(), an expired-but-not-overwritten registration is still destroyable,
+            // so its tier/referral slots are freed without waiting for someone to re-register
+            let registration: Registration = self
+                .registrations
+                .get(caller)
+                .ok_or(AzEventRegistrationError::NotFound("Registration".to_string()))?;
 
-            self.registrations.remove(caller);
+            self.reap(caller, registration);
 
             Ok(())
         }
 
         #[ink(message)]
-        pub fn register(&mut self, referrer: Option<AccountId>) -> Result<Registration> {
+        pub fn upgrade_registration(&mut self, address: AccountId) -> Result<Registration> {
+            let caller: AccountId = Self::env().caller();
+            Self::authorise(caller, self.admin)?;
+
+            let mut registration: Registration = self.show(address)?;
+            if registration.tier == Tier::Vip {
+                return Err(AzEventRegistrationError::UnprocessableEntity(
+                    "Already Vip".to_string(),
+                ));
+            }
+            self.change_tier(Some(registration.tier), Tier::Vip)?;
+            registration.tier = Tier::Vip;
+            self.registrations.insert(address, &registration);
+
+            Self::emit_event(self.env(), Event::Upgrade(Upgrade { address }));
+
+            Ok(registration)
+        }
+
+        #[ink(message)]
+        pub fn register(
+            &mut self,
+            referrer: Option<AccountId>,
+            ttl: Timestamp,
+        ) -> Result<Registration> {
             let caller: AccountId = Self::env().caller();
             let block_timestamp: Timestamp = Self::env().block_timestamp();
+            if self.invitations_only {
+                return Err(AzEventRegistrationError::UnprocessableEntity(
+                    "Registration requires an invitation".to_string(),
+                ));
+            }
             if block_timestamp > self.deadline {
                 return Err(AzEventRegistrationError::UnprocessableEntity(
                     "Registration is now closed".to_string(),
                 ));
             }
+            if !(MIN_TTL..=MAX_TTL).contains(&ttl) {
+                return Err(AzEventRegistrationError::UnprocessableEntity(
+                    "Ttl is out of range".to_string(),
+                ));
+            }
             if let Some(referrer_unwrapped) = referrer {
                 if referrer_unwrapped == caller {
                     return Err(AzEventRegistrationError::UnprocessableEntity(
@@ -110,17 +325,90 @@ mod az_event_registration {
                     ));
                 }
             }
-            if self.registrations.get(caller).is_some() {
+            let existing: Option<Registration> = self.registrations.get(caller);
+            let existing_referrer: Option<AccountId> = existing.as_ref().and_then(|r| r.referrer);
+            let (index, index_is_new) = self.allocate_index(caller, block_timestamp)?;
+            if let Err(e) = self.change_tier(existing.as_ref().map(|r| r.tier), Tier::Standard) {
+                if index_is_new {
+                    self.release_index(caller, index);
+                }
+                return Err(e);
+            }
+            let registration = Registration {
+                address: caller,
+                referrer,
+                expires_at: block_timestamp + ttl,
+                tier: Tier::Standard,
+            };
+            self.registrations.insert(caller, &registration);
+            self.apply_referral_transition(existing_referrer, referrer);
+
+            Self::emit_event(
+                self.env(),
+                Event::Register(Register {
+                    address: caller,
+                    referrer,
+                }),
+            );
+
+            Ok(registration)
+        }
+
+        #[ink(message)]
+        pub fn register_with_invitation(
+            &mut self,
+            code: Hash,
+            referrer: Option<AccountId>,
+        ) -> Result<Registration> {
+            let caller: AccountId = Self::env().caller();
+            let block_timestamp: Timestamp = Self::env().block_timestamp();
+            if block_timestamp > self.deadline {
+                return Err(AzEventRegistrationError::UnprocessableEntity(
+                    "Registration is now closed".to_string(),
+                ));
+            }
+            let mut invitation: Invitation = self
+                .invitations
+                .get(code)
+                .ok_or(AzEventRegistrationError::NotFound("Invitation".to_string()))?;
+            if invitation.used >= invitation.max_uses {
                 return Err(AzEventRegistrationError::UnprocessableEntity(
-                    "Registration already exists".to_string(),
+                    "Invitation has no uses remaining".to_string(),
                 ));
             }
+            if block_timestamp > invitation.expiry {
+                return Err(AzEventRegistrationError::UnprocessableEntity(
+                    "Invitation has expired".to_string(),
+                ));
+            }
+            if let Some(referrer_unwrapped) = referrer {
+                if referrer_unwrapped == caller {
+                    return Err(AzEventRegistrationError::UnprocessableEntity(
+                        "Registrant and referrer must be different".to_string(),
+                    ));
+                }
+            }
 
+            let existing: Option<Registration> = self.registrations.get(caller);
+            let existing_referrer: Option<AccountId> = existing.as_ref().and_then(|r| r.referrer);
+            let (index, index_is_new) = self.allocate_index(caller, block_timestamp)?;
+            if let Err(e) = self.change_tier(existing.as_ref().map(|r| r.tier), Tier::Standard) {
+                if index_is_new {
+                    self.release_index(caller, index);
+                }
+                return Err(e);
+            }
             let registration = Registration {
                 address: caller,
                 referrer,
+                expires_at: invitation.expiry,
+                tier: Tier::Standard,
             };
             self.registrations.insert(caller, &registration);
+            self.apply_referral_transition(existing_referrer, referrer);
+
+            invitation.used += 1;
+            self.invitations.insert(code, &invitation);
 
             Self::emit_event(
                 self.env(),
@@ -134,11 +422,46 @@ mod az_event_registration {
         }
 
         #[ink(message)]
-        pub fn update(&mut self, referrer: Option<AccountId>) -> Result<Registration> {
+        pub fn add_invitation(
+            &mut self,
+            code: Hash,
+            max_uses: u32,
+            expiry: Timestamp,
+        ) -> Result<Invitation> {
+            let caller: AccountId = Self::env().caller();
+            Self::authorise(caller, self.admin)?;
+
+            let invitation = Invitation {
+                max_uses,
+                used: 0,
+                expiry,
+            };
+            if self.invitations.get(code).is_none() {
+                self.invitation_codes.push(code);
+            }
+            self.invitations.insert(code, &invitation);
+
+            Ok(invitation)
+        }
+
+        #[ink(message)]
+        pub fn update(
+            &mut self,
+            referrer: Option<AccountId>,
+            ttl: Timestamp,
+        ) -> Result<Registration> {
             let caller: AccountId = Self::env().caller();
+            if !(MIN_TTL..=MAX_TTL).contains(&ttl) {
+                return Err(AzEventRegistrationError::UnprocessableEntity(
+                    "Ttl is out of range".to_string(),
+                ));
+            }
             let mut registration: Registration = self.show(caller)?;
+            let old_referrer = registration.referrer;
             registration.referrer = referrer;
+            registration.expires_at = Self::env().block_timestamp() + ttl;
             self.registrations.insert(caller, &registration);
+            self.apply_referral_transition(old_referrer, referrer);
 
             Self::emit_event(
                 self.env(),
@@ -152,11 +475,12 @@ mod az_event_registration {
         }
 
         #[ink(message)]
-        pub fn update_config(&mut self, deadline: Timestamp) -> Result<()> {
+        pub fn update_config(&mut self, deadline: Timestamp, invitations_only: bool) -> Result<()> {
             let caller: AccountId = Self::env().caller();
             Self::authorise(caller, self.admin)?;
 
             self.deadline = deadline;
+            self.invitations_only = invitations_only;
 
             Ok(())
         }
@@ -169,6 +493,136 @@ mod az_event_registration {
             Ok(())
         }
 
+        // returns the index to use, and whether it was newly allocated (as opposed to reused
+        // from an existing, expired registration)
+        fn allocate_index(
+            &mut self,
+            caller: AccountId,
+            block_timestamp: Timestamp,
+        ) -> Result<(u64, bool)> {
+            match self.registrations.get(caller) {
+                Some(existing) if existing.expires_at >= block_timestamp => {
+                    Err(AzEventRegistrationError::UnprocessableEntity(
+                        "Registration already exists".to_string(),
+                    ))
+                }
+                Some(_existing) => {
+                    let index = self.registration_indices.get(caller);
+                    debug_assert!(index.is_some(), "a stored registration must have an index");
+                    Ok((index.unwrap_or(0), false))
+                }
+                None => {
+                    let new_index = self.registration_count;
+                    self.registration_index.insert(new_index, &caller);
+                    self.registration_indices.insert(caller, &new_index);
+                    self.registration_count += 1;
+                    Ok((new_index, true))
+                }
+            }
+        }
+
+        // undoes a freshly allocated index when a later step in the same registration fails,
+        // so a rejected registration leaves no storage trace. Must only be called with the
+        // index just returned by allocate_index in the same call, with nothing else mutating
+        // registration_count in between, since it assumes `index` is the current top entry.
+        fn release_index(&mut self, caller: AccountId, index: u64) {
+            debug_assert_eq!(index, self.registration_count - 1);
+            self.registration_index.remove(index);
+            self.registration_indices.remove(caller);
+            self.registration_count -= 1;
+        }
+
+        fn apply_referral_transition(
+            &mut self,
+            old_referrer: Option<AccountId>,
+            new_referrer: Option<AccountId>,
+        ) {
+            if old_referrer == new_referrer {
+                return;
+            }
+            if let Some(old_referrer_unwrapped) = old_referrer {
+                self.decrement_referral_count(old_referrer_unwrapped);
+            }
+            if let Some(new_referrer_unwrapped) = new_referrer {
+                self.increment_referral_count(new_referrer_unwrapped);
+            }
+        }
+
+        fn increment_referral_count(&mut self, referrer: AccountId) {
+            // a count of 0 doesn't mean "never indexed" — decrement_referral_count can take a
+            // previously-indexed referrer back down to 0 without removing their index slot, so
+            // indexing is tracked separately to avoid pushing a duplicate referrer_index entry
+            if self.referrer_indexed.get(referrer).is_none() {
+                self.referrer_index.insert(self.referrer_count, &referrer);
+                self.referrer_count += 1;
+                self.referrer_indexed.insert(referrer, &());
+            }
+            let count = self.referral_counts.get(referrer).unwrap_or(0);
+            self.referral_counts.insert(referrer, &(count + 1));
+        }
+
+        fn decrement_referral_count(&mut self, referrer: AccountId) {
+            let count = self.referral_counts.get(referrer).unwrap_or(0);
+            self.referral_counts.insert(referrer, &count.saturating_sub(1));
+        }
+
+        fn change_tier(&mut self, old_tier: Option<Tier>, new_tier: Tier) -> Result<()> {
+            if old_tier == Some(new_tier) {
+                return Ok(());
+            }
+            if self.tier_count(new_tier) >= self.tier_cap(new_tier) {
+                return Err(AzEventRegistrationError::UnprocessableEntity(
+                    "Capacity reached".to_string(),
+                ));
+            }
+            if let Some(old_tier_unwrapped) = old_tier {
+                self.decrement_tier_count(old_tier_unwrapped);
+            }
+            self.increment_tier_count(new_tier);
+
+            Ok(())
+        }
+
+        fn tier_count(&self, tier: Tier) -> u32 {
+            match tier {
+                Tier::Standard => self.standard_count,
+                Tier::Vip => self.vip_count,
+            }
+        }
+
+        fn tier_cap(&self, tier: Tier) -> u32 {
+            match tier {
+                Tier::Standard => self.standard_cap,
+                Tier::Vip => self.vip_cap,
+            }
+        }
+
+        fn increment_tier_count(&mut self, tier: Tier) {
+            match tier {
+                Tier::Standard => self.standard_count += 1,
+                Tier::Vip => self.vip_count += 1,
+            }
+        }
+
+        fn decrement_tier_count(&mut self, tier: Tier) {
+            match tier {
+                Tier::Standard => self.standard_count = self.standard_count.saturating_sub(1),
+                Tier::Vip => self.vip_count = self.vip_count.saturating_sub(1),
+            }
+        }
+
+        fn reap(&mut self, address: AccountId, registration: Registration) {
+            self.registrations.remove(address);
+            if let Some(index) = self.registration_indices.get(address) {
+                self.registration_index.remove(index);
+                self.registration_indices.remove(address);
+            }
+            if let Some(referrer) = registration.referrer {
+                self.decrement_referral_count(referrer);
+            }
+            self.decrement_tier_count(registration.tier);
+        }
+
         fn emit_event<EE: EmitEvent<Self>>(emitter: EE, event: Event) {
             emitter.emit_event(event);
         }
@@ -183,12 +637,20 @@ mod az_event_registration {
         };
 
         const MOCK_DEAD_LINE: Timestamp = 654654;
+        const MOCK_TTL: Timestamp = MIN_TTL;
+        const MOCK_STANDARD_CAP: u32 = 10;
+        const MOCK_VIP_CAP: u32 = 10;
 
         // === HELPERS ===
         fn init() -> (DefaultAccounts<DefaultEnvironment>, AzEventRegistration) {
             let accounts = default_accounts();
             set_caller::<DefaultEnvironment>(accounts.bob);
-            let az_event_registration = AzEventRegistration::new(MOCK_DEAD_LINE);
+            let az_event_registration = AzEventRegistration::new(
+                MOCK_DEAD_LINE,
+                false,
+                MOCK_STANDARD_CAP,
+                MOCK_VIP_CAP,
+            );
             (accounts, az_event_registration)
         }
 
@@ -200,7 +662,10 @@ mod az_event_registration {
             let config = az_event_registration.config();
             // * it returns the config
             assert_eq!(config.admin, accounts.bob);
-            assert_eq!(config.deadline, MOCK_DEAD_LINE)
+            assert_eq!(config.deadline, MOCK_DEAD_LINE);
+            assert!(!config.invitations_only);
+            assert_eq!(config.standard_cap, MOCK_STANDARD_CAP);
+            assert_eq!(config.vip_cap, MOCK_VIP_CAP);
         }
 
         // === TEST HANDLES ===
@@ -210,7 +675,9 @@ mod az_event_registration {
             let referrer: Option<AccountId> = None;
             // when registration does not exist
             // * it raises an error
-            let mut result = az_event_registration.update(referrer);
+            let mut result = az_event_registration
+                .destroy()
+                .and_then(|()| az_event_registration.show(accounts.bob));
             assert_eq!(
                 result,
                 Err(AzEventRegistrationError::NotFound(
@@ -218,7 +685,7 @@ mod az_event_registration {
                 ))
             );
             // when registration exists
-            result = az_event_registration.register(referrer);
+            result = az_event_registration.register(referrer, MOCK_TTL);
             result.unwrap();
             // * it destroys the registration
             az_event_registration.destroy().unwrap();
@@ -229,6 +696,16 @@ mod az_event_registration {
                     "Registration".to_string()
                 ))
             );
+            // when the registration exists but has expired
+            // * it still destroys it, freeing its tier and referral slots
+            az_event_registration
+                .register(Some(accounts.alice), MOCK_TTL)
+                .unwrap();
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                ink::env::block_timestamp::<DefaultEnvironment>() + MOCK_TTL + 1,
+            );
+            az_event_registration.destroy().unwrap();
+            assert_eq!(az_event_registration.referral_count(accounts.alice), 0);
         }
 
         #[ink::test]
@@ -239,7 +716,7 @@ mod az_event_registration {
             ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
                 az_event_registration.deadline + 1,
             );
-            let mut result = az_event_registration.register(referrer);
+            let mut result = az_event_registration.register(referrer, MOCK_TTL);
             assert_eq!(
                 result,
                 Err(AzEventRegistrationError::UnprocessableEntity(
@@ -250,51 +727,145 @@ mod az_event_registration {
             ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
                 az_event_registration.deadline,
             );
-            // = when registration does not exist
-            // == when referrer is present
-            // === when referrer is different to caller
-            // ==== * it create a new registration
-            result = az_event_registration.register(referrer);
+            // = when ttl is out of range
+            // = * it raises an error
+            result = az_event_registration.register(referrer, MIN_TTL - 1);
+            assert_eq!(
+                result,
+                Err(AzEventRegistrationError::UnprocessableEntity(
+                    "Ttl is out of range".to_string()
+                ))
+            );
+            result = az_event_registration.register(referrer, MAX_TTL + 1);
+            assert_eq!(
+                result,
+                Err(AzEventRegistrationError::UnprocessableEntity(
+                    "Ttl is out of range".to_string()
+                ))
+            );
+            // = when ttl is in range
+            // == when registration does not exist
+            // === when referrer is present
+            // ==== when referrer is different to caller
+            // ===== * it creates a new registration
+            let registered_at = 1;
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(registered_at);
+            result = az_event_registration.register(referrer, MOCK_TTL);
             let mut result_unwrapped = result.unwrap();
             assert_eq!(
                 result_unwrapped,
                 Registration {
                     address: accounts.bob,
-                    referrer
+                    referrer,
+                    expires_at: registered_at + MOCK_TTL,
+                    tier: Tier::Standard,
                 }
             );
-            // === when referrer is same as caller
+            // ==== when referrer is same as caller
             referrer = Some(accounts.bob);
-            // ==== * it raises an error
-            result = az_event_registration.register(referrer);
+            // ===== * it raises an error
+            result = az_event_registration.register(referrer, MOCK_TTL);
             assert_eq!(
                 result,
                 Err(AzEventRegistrationError::UnprocessableEntity(
                     "Registrant and referrer must be different".to_string()
                 ))
             );
-            // == when referrer is blank
+            // === when referrer is blank
             referrer = None;
-            // == * it create a new registration
+            // === * it creates a new registration
             set_caller::<DefaultEnvironment>(accounts.charlie);
-            result = az_event_registration.register(referrer);
+            result = az_event_registration.register(referrer, MOCK_TTL);
             result_unwrapped = result.unwrap();
             assert_eq!(
                 result_unwrapped,
                 Registration {
                     address: accounts.charlie,
-                    referrer
+                    referrer,
+                    expires_at: registered_at + MOCK_TTL,
+                    tier: Tier::Standard,
                 }
             );
-            // = when registration exists
-            // = * it raises an error
-            result = az_event_registration.register(referrer);
+            // == when registration exists
+            // === when the existing registration has not expired
+            // === * it raises an error
+            result = az_event_registration.register(referrer, MOCK_TTL);
             assert_eq!(
                 result,
                 Err(AzEventRegistrationError::UnprocessableEntity(
                     "Registration already exists".to_string()
                 ))
             );
+            // === when the existing registration has expired
+            // === * it overwrites the registration
+            let re_registered_at = registered_at + MOCK_TTL + 1;
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(re_registered_at);
+            result = az_event_registration.register(referrer, MOCK_TTL);
+            result_unwrapped = result.unwrap();
+            assert_eq!(
+                result_unwrapped,
+                Registration {
+                    address: accounts.charlie,
+                    referrer,
+                    expires_at: re_registered_at + MOCK_TTL,
+                    tier: Tier::Standard,
+                }
+            );
+        }
+
+        #[ink::test]
+        fn test_register_invitations_only() {
+            let (_accounts, mut az_event_registration) = init();
+            az_event_registration
+                .update_config(az_event_registration.deadline, true)
+                .unwrap();
+            // when invitations_only is true
+            // * it raises an error, even when ttl and referrer would otherwise be valid
+            let result = az_event_registration.register(None, MOCK_TTL);
+            assert_eq!(
+                result,
+                Err(AzEventRegistrationError::UnprocessableEntity(
+                    "Registration requires an invitation".to_string()
+                ))
+            );
+        }
+
+        #[ink::test]
+        fn test_registrations() {
+            let (accounts, mut az_event_registration) = init();
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1);
+            az_event_registration.register(None, MOCK_TTL).unwrap();
+            set_caller::<DefaultEnvironment>(accounts.charlie);
+            az_event_registration.register(None, MOCK_TTL).unwrap();
+            set_caller::<DefaultEnvironment>(accounts.django);
+            az_event_registration.register(None, MOCK_TTL).unwrap();
+            // when cookie is None
+            // * it returns up to limit entries from the start
+            let (mut result, mut next_cookie) = az_event_registration.registrations(None, 2);
+            assert_eq!(result.len(), 2);
+            assert_eq!(result[0].address, accounts.bob);
+            assert_eq!(result[1].address, accounts.charlie);
+            assert_eq!(next_cookie, Some(1));
+            // when cookie is present
+            // * it returns entries after the cookie
+            (result, next_cookie) = az_event_registration.registrations(next_cookie, 2);
+            assert_eq!(result.len(), 1);
+            assert_eq!(result[0].address, accounts.django);
+            assert_eq!(next_cookie, None);
+            // when an entry has been destroyed
+            // * it is skipped
+            set_caller::<DefaultEnvironment>(accounts.charlie);
+            az_event_registration.destroy().unwrap();
+            (result, next_cookie) = az_event_registration.registrations(None, 10);
+            assert_eq!(result.len(), 2);
+            assert_eq!(result[0].address, accounts.bob);
+            assert_eq!(result[1].address, accounts.django);
+            assert_eq!(next_cookie, None);
+            // when limit is 0
+            // * it returns an empty page instead of the full remaining set
+            (result, next_cookie) = az_event_registration.registrations(None, 0);
+            assert_eq!(result.len(), 0);
+            assert_eq!(next_cookie, None);
         }
 
         #[ink::test]
@@ -303,7 +874,7 @@ mod az_event_registration {
             let mut referrer: Option<AccountId> = None;
             // when registration does not exist
             // * it raises an error
-            let mut result = az_event_registration.update(referrer);
+            let mut result = az_event_registration.update(referrer, MOCK_TTL);
             assert_eq!(
                 result,
                 Err(AzEventRegistrationError::NotFound(
@@ -311,32 +882,46 @@ mod az_event_registration {
                 ))
             );
             // when registration exists
-            result = az_event_registration.register(referrer);
+            result = az_event_registration.register(referrer, MOCK_TTL);
             result.unwrap();
-            // = when registrater does not have a reffer
-            // == when adding a new referrer
-            // == * it updates the referrer
+            // = when ttl is out of range
+            // = * it raises an error
+            result = az_event_registration.update(referrer, MIN_TTL - 1);
+            assert_eq!(
+                result,
+                Err(AzEventRegistrationError::UnprocessableEntity(
+                    "Ttl is out of range".to_string()
+                ))
+            );
+            // = when ttl is in range
+            // == when registrater does not have a reffer
+            // === when adding a new referrer
+            // === * it updates the referrer
             referrer = Some(accounts.charlie);
-            result = az_event_registration.update(referrer);
+            result = az_event_registration.update(referrer, MOCK_TTL);
             let mut result_unwrapped = result.unwrap();
             assert_eq!(
                 result_unwrapped,
                 Registration {
                     address: accounts.bob,
-                    referrer
+                    referrer,
+                    expires_at: ink::env::block_timestamp::<DefaultEnvironment>() + MOCK_TTL,
+                    tier: Tier::Standard,
                 }
             );
-            // = when registrater has a reffer
-            // == when removing the referrer
-            // == * it updates the referrer
+            // == when registrater has a reffer
+            // === when removing the referrer
+            // === * it updates the referrer
             referrer = None;
-            result = az_event_registration.update(referrer);
+            result = az_event_registration.update(referrer, MOCK_TTL);
             result_unwrapped = result.unwrap();
             assert_eq!(
                 result_unwrapped,
                 Registration {
                     address: accounts.bob,
-                    referrer
+                    referrer,
+                    expires_at: ink::env::block_timestamp::<DefaultEnvironment>() + MOCK_TTL,
+                    tier: Tier::Standard,
                 }
             );
         }
@@ -348,13 +933,323 @@ mod az_event_registration {
             // when called by non-admin
             set_caller::<DefaultEnvironment>(accounts.charlie);
             // * it raises an error
-            let result = az_event_registration.update_config(new_deadline);
+            let result = az_event_registration.update_config(new_deadline, true);
             assert_eq!(result, Err(AzEventRegistrationError::Unauthorised));
             // when called by admin
             set_caller::<DefaultEnvironment>(accounts.bob);
             // * it updates the config
-            az_event_registration.update_config(new_deadline).unwrap();
+            az_event_registration
+                .update_config(new_deadline, true)
+                .unwrap();
             assert_eq!(az_event_registration.deadline, new_deadline);
+            assert!(az_event_registration.invitations_only);
+        }
+
+        #[ink::test]
+        fn test_add_invitation() {
+            let (accounts, mut az_event_registration) = init();
+            let code: Hash = Hash::from([1u8; 32]);
+            // when called by non-admin
+            set_caller::<DefaultEnvironment>(accounts.charlie);
+            // * it raises an error
+            let result = az_event_registration.add_invitation(code, 5, MOCK_DEAD_LINE);
+            assert_eq!(result, Err(AzEventRegistrationError::Unauthorised));
+            // when called by admin
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            // * it creates the invitation
+            let invitation = az_event_registration
+                .add_invitation(code, 5, MOCK_DEAD_LINE)
+                .unwrap();
+            assert_eq!(
+                invitation,
+                Invitation {
+                    max_uses: 5,
+                    used: 0,
+                    expiry: MOCK_DEAD_LINE,
+                }
+            );
+            assert_eq!(
+                az_event_registration.list_invitations(),
+                Vec::from([(code, invitation)])
+            );
+        }
+
+        #[ink::test]
+        fn test_register_with_invitation() {
+            let (accounts, mut az_event_registration) = init();
+            let code: Hash = Hash::from([1u8; 32]);
+            let referrer: Option<AccountId> = None;
+            let invitation_expiry: Timestamp = MOCK_DEAD_LINE - 1;
+            // when the invitation does not exist
+            // * it raises an error
+            let mut result = az_event_registration.register_with_invitation(code, referrer);
+            assert_eq!(
+                result,
+                Err(AzEventRegistrationError::NotFound("Invitation".to_string()))
+            );
+            az_event_registration
+                .add_invitation(code, 2, invitation_expiry)
+                .unwrap();
+            // when the invitation has expired
+            // * it raises an error
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                invitation_expiry + 1,
+            );
+            result = az_event_registration.register_with_invitation(code, referrer);
+            assert_eq!(
+                result,
+                Err(AzEventRegistrationError::UnprocessableEntity(
+                    "Invitation has expired".to_string()
+                ))
+            );
+            // when the invitation has not expired
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1);
+            // * it registers the caller
+            let result_unwrapped = az_event_registration
+                .register_with_invitation(code, referrer)
+                .unwrap();
+            assert_eq!(
+                result_unwrapped,
+                Registration {
+                    address: accounts.bob,
+                    referrer,
+                    expires_at: invitation_expiry,
+                    tier: Tier::Standard,
+                }
+            );
+            // when the current block timestamp is greater than the registration deadline
+            // * it raises an error, even with a valid invitation
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                MOCK_DEAD_LINE + 1,
+            );
+            set_caller::<DefaultEnvironment>(accounts.charlie);
+            result = az_event_registration.register_with_invitation(code, referrer);
+            assert_eq!(
+                result,
+                Err(AzEventRegistrationError::UnprocessableEntity(
+                    "Registration is now closed".to_string()
+                ))
+            );
+            // when the invitation has no uses remaining
+            // * it raises an error
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1);
+            az_event_registration
+                .register_with_invitation(code, referrer)
+                .unwrap();
+            set_caller::<DefaultEnvironment>(accounts.django);
+            result = az_event_registration.register_with_invitation(code, referrer);
+            assert_eq!(
+                result,
+                Err(AzEventRegistrationError::UnprocessableEntity(
+                    "Invitation has no uses remaining".to_string()
+                ))
+            );
+        }
+
+        #[ink::test]
+        fn test_referral_count() {
+            let (accounts, mut az_event_registration) = init();
+            // when the account has never been referred
+            // * it returns 0
+            assert_eq!(az_event_registration.referral_count(accounts.alice), 0);
+            // when the account has been referred
+            // * it returns the count
+            az_event_registration
+                .register(Some(accounts.alice), MOCK_TTL)
+                .unwrap();
+            assert_eq!(az_event_registration.referral_count(accounts.alice), 1);
+        }
+
+        #[ink::test]
+        fn test_referral_count_transitions() {
+            let (accounts, mut az_event_registration) = init();
+            // bob registers with alice as referrer
+            az_event_registration
+                .register(Some(accounts.alice), MOCK_TTL)
+                .unwrap();
+            assert_eq!(az_event_registration.referral_count(accounts.alice), 1);
+            // = when the referrer changes from Some(a) to Some(b)
+            // = * it decrements a and increments b
+            az_event_registration
+                .update(Some(accounts.charlie), MOCK_TTL)
+                .unwrap();
+            assert_eq!(az_event_registration.referral_count(accounts.alice), 0);
+            assert_eq!(az_event_registration.referral_count(accounts.charlie), 1);
+            // = when the referrer changes from Some(a) to None
+            // = * it decrements a
+            az_event_registration.update(None, MOCK_TTL).unwrap();
+            assert_eq!(az_event_registration.referral_count(accounts.charlie), 0);
+            // = when the referrer changes from None to Some(b)
+            // = * it increments b
+            az_event_registration
+                .update(Some(accounts.alice), MOCK_TTL)
+                .unwrap();
+            assert_eq!(az_event_registration.referral_count(accounts.alice), 1);
+            // = when the registration is destroyed
+            // = * it decrements the referrer
+            az_event_registration.destroy().unwrap();
+            assert_eq!(az_event_registration.referral_count(accounts.alice), 0);
+        }
+
+        #[ink::test]
+        fn test_top_referrers() {
+            let (accounts, mut az_event_registration) = init();
+            // alice and eve also register themselves here, but that's incidental: a
+            // referrer is ranked purely off referral_counts, whether or not they have
+            // an active registration of their own (see
+            // test_top_referrers_agrees_with_referral_count_after_referrer_expires)
+            set_caller::<DefaultEnvironment>(accounts.alice);
+            az_event_registration.register(None, MOCK_TTL).unwrap();
+            set_caller::<DefaultEnvironment>(accounts.eve);
+            az_event_registration.register(None, MOCK_TTL).unwrap();
+            // bob and charlie both refer alice, django refers eve
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            az_event_registration
+                .register(Some(accounts.alice), MOCK_TTL)
+                .unwrap();
+            set_caller::<DefaultEnvironment>(accounts.charlie);
+            az_event_registration
+                .register(Some(accounts.alice), MOCK_TTL)
+                .unwrap();
+            set_caller::<DefaultEnvironment>(accounts.django);
+            az_event_registration
+                .register(Some(accounts.eve), MOCK_TTL)
+                .unwrap();
+            // when cookie is None
+            // * it returns the top referrers, highest count first
+            let (mut result, mut next_cookie) = az_event_registration.top_referrers(None, 1);
+            assert_eq!(result, Vec::from([(accounts.alice, 2)]));
+            assert_eq!(next_cookie, Some(0));
+            // when cookie is present
+            // * it returns the next page
+            (result, next_cookie) = az_event_registration.top_referrers(next_cookie, 1);
+            assert_eq!(result, Vec::from([(accounts.eve, 1)]));
+            assert_eq!(next_cookie, None);
+            // when limit is 0
+            // * it returns an empty page instead of the full ranked list
+            (result, next_cookie) = az_event_registration.top_referrers(None, 0);
+            assert_eq!(result.len(), 0);
+            assert_eq!(next_cookie, None);
+        }
+
+        #[ink::test]
+        fn test_top_referrers_no_duplicate_entry_after_count_returns_to_zero_and_back() {
+            let (accounts, mut az_event_registration) = init();
+            set_caller::<DefaultEnvironment>(accounts.alice);
+            az_event_registration.register(None, MOCK_TTL).unwrap();
+            // bob refers alice, then moves his referrer away, taking alice's count to 0
+            // without removing her existing referrer_index slot
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            az_event_registration
+                .register(Some(accounts.alice), MOCK_TTL)
+                .unwrap();
+            az_event_registration.update(None, MOCK_TTL).unwrap();
+            assert_eq!(az_event_registration.referral_count(accounts.alice), 0);
+            // django refers alice again, taking her count back up from 0
+            set_caller::<DefaultEnvironment>(accounts.django);
+            az_event_registration
+                .register(Some(accounts.alice), MOCK_TTL)
+                .unwrap();
+            // when an account's count goes to 0 and back to positive
+            // * it is ranked once, not once per time its count left and returned to 0
+            let (result, _) = az_event_registration.top_referrers(None, 10);
+            assert_eq!(result, Vec::from([(accounts.alice, 1)]));
+        }
+
+        #[ink::test]
+        fn test_top_referrers_agrees_with_referral_count_after_referrer_expires() {
+            let (accounts, mut az_event_registration) = init();
+            set_caller::<DefaultEnvironment>(accounts.alice);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1);
+            az_event_registration.register(None, MOCK_TTL).unwrap();
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            az_event_registration
+                .register(Some(accounts.alice), MOCK_TTL)
+                .unwrap();
+            // when alice's own registration has since expired
+            // * referral_count and top_referrers still agree on her count
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                1 + MOCK_TTL + 1,
+            );
+            assert_eq!(az_event_registration.referral_count(accounts.alice), 1);
+            let (result, _) = az_event_registration.top_referrers(None, 10);
+            assert_eq!(result, Vec::from([(accounts.alice, 1)]));
+        }
+
+        #[ink::test]
+        fn test_register_tier_capacity() {
+            let accounts = default_accounts();
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            let mut az_event_registration =
+                AzEventRegistration::new(MOCK_DEAD_LINE, false, 1, MOCK_VIP_CAP);
+            // when the standard tier cap has not been reached
+            // * it registers the caller
+            az_event_registration.register(None, MOCK_TTL).unwrap();
+            // when the standard tier cap has been reached
+            // * it raises an error
+            set_caller::<DefaultEnvironment>(accounts.charlie);
+            let result = az_event_registration.register(None, MOCK_TTL);
+            assert_eq!(
+                result,
+                Err(AzEventRegistrationError::UnprocessableEntity(
+                    "Capacity reached".to_string()
+                ))
+            );
+            // * it leaves no trace of the rejected registration
+            let (registrations, _) = az_event_registration.registrations(None, 10);
+            assert_eq!(registrations.len(), 1);
+            assert_eq!(registrations[0].address, accounts.bob);
+            // when a standard registration is destroyed, freeing a slot
+            // * it allows a new registration into the tier
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            az_event_registration.destroy().unwrap();
+            set_caller::<DefaultEnvironment>(accounts.charlie);
+            az_event_registration.register(None, MOCK_TTL).unwrap();
+        }
+
+        #[ink::test]
+        fn test_upgrade_registration() {
+            let accounts = default_accounts();
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            let mut az_event_registration =
+                AzEventRegistration::new(MOCK_DEAD_LINE, false, MOCK_STANDARD_CAP, 1);
+            az_event_registration.register(None, MOCK_TTL).unwrap();
+            set_caller::<DefaultEnvironment>(accounts.charlie);
+            az_event_registration.register(None, MOCK_TTL).unwrap();
+            // when called by non-admin
+            // * it raises an error
+            let mut result = az_event_registration.upgrade_registration(accounts.bob);
+            assert_eq!(result, Err(AzEventRegistrationError::Unauthorised));
+            // when called by admin
+            set_caller::<DefaultEnvironment>(accounts.bob);
+            // = when the vip tier cap has not been reached
+            // = * it moves the registration to the vip tier
+            let result_unwrapped = az_event_registration
+                .upgrade_registration(accounts.bob)
+                .unwrap();
+            assert_eq!(result_unwrapped.tier, Tier::Vip);
+            assert_eq!(
+                az_event_registration.show(accounts.bob).unwrap().tier,
+                Tier::Vip
+            );
+            // = when the registration is already Vip
+            // = * it raises an error instead of emitting a no-op Upgrade event
+            result = az_event_registration.upgrade_registration(accounts.bob);
+            assert_eq!(
+                result,
+                Err(AzEventRegistrationError::UnprocessableEntity(
+                    "Already Vip".to_string()
+                ))
+            );
+            // = when the vip tier cap has been reached
+            // = * it raises an error
+            result = az_event_registration.upgrade_registration(accounts.charlie);
+            assert_eq!(
+                result,
+                Err(AzEventRegistrationError::UnprocessableEntity(
+                    "Capacity reached".to_string()
+                ))
+            );
         }
     }
 }